@@ -0,0 +1,397 @@
+//! Compositor abstraction so output discovery and hint-to-connector
+//! resolution work the same way regardless of which Wayland compositor (or
+//! none, for a plain XWayland session) is running. `set_primary` still
+//! always drives `xrandr`, since the XWayland primary is the thing being
+//! switched either way.
+
+use std::env;
+use std::process::{Command, Stdio};
+
+use crate::notify_info;
+use crate::sway_ipc::IpcConnection;
+
+/// An output as reported by a compositor, normalized across Sway/niri/Hyprland's
+/// differently-shaped JSON.
+#[derive(Debug, Clone)]
+pub struct OutputInfo {
+    pub connector: String,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Which compositor to talk to, either picked explicitly via `--compositor`
+/// or probed from the environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompositorKind {
+    Sway,
+    Niri,
+    Hyprland,
+}
+
+pub fn parse_compositor_flag(s: &str) -> Option<CompositorKind> {
+    match s.to_lowercase().as_str() {
+        "sway" => Some(CompositorKind::Sway),
+        "niri" => Some(CompositorKind::Niri),
+        "hyprland" => Some(CompositorKind::Hyprland),
+        _ => None,
+    }
+}
+
+/// Probe `$SWAYSOCK` / `$NIRI_SOCKET` / `$HYPRLAND_INSTANCE_SIGNATURE` to
+/// guess which compositor is running.
+pub fn detect() -> Option<CompositorKind> {
+    if env::var_os("SWAYSOCK").is_some() {
+        Some(CompositorKind::Sway)
+    } else if env::var_os("NIRI_SOCKET").is_some() {
+        Some(CompositorKind::Niri)
+    } else if env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        Some(CompositorKind::Hyprland)
+    } else {
+        None
+    }
+}
+
+pub fn backend_for(kind: CompositorKind) -> Box<dyn CompositorBackend> {
+    match kind {
+        CompositorKind::Sway => Box::new(SwayBackend),
+        CompositorKind::Niri => Box::new(NiriBackend),
+        CompositorKind::Hyprland => Box::new(HyprlandBackend),
+    }
+}
+
+/// A compositor we can list outputs from and resolve config hints against.
+pub trait CompositorBackend {
+    /// List the compositor's current outputs.
+    fn list_outputs(&self) -> Vec<OutputInfo>;
+
+    /// Resolve a user-facing hint (connector name, or a make/model/serial
+    /// identity) to a connector name via scored identification. The default
+    /// implementation is shared by every backend; only `list_outputs` needs
+    /// a compositor-specific implementation.
+    fn resolve_hint(&self, hint: &str) -> Option<String> {
+        resolve_hint_scored(&self.list_outputs(), hint)
+    }
+}
+
+/* ------------------------------- Sway ------------------------------- */
+
+pub struct SwayBackend;
+
+impl CompositorBackend for SwayBackend {
+    fn list_outputs(&self) -> Vec<OutputInfo> {
+        IpcConnection::connect()
+            .and_then(|mut c| c.get_outputs())
+            .map(|outs| {
+                outs.into_iter()
+                    .map(|o| OutputInfo {
+                        connector: o.name,
+                        make: o.make,
+                        model: o.model,
+                        serial: o.serial,
+                        description: o.description,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/* ------------------------------- niri -------------------------------- */
+
+pub struct NiriBackend;
+
+impl CompositorBackend for NiriBackend {
+    fn list_outputs(&self) -> Vec<OutputInfo> {
+        // `niri msg --json outputs` returns a JSON object keyed by connector
+        // name, not an array.
+        let Some(obj) = run_json("niri", &["msg", "--json", "outputs"]) else {
+            return Vec::new();
+        };
+        let Some(obj) = obj.as_object() else {
+            return Vec::new();
+        };
+        obj.iter()
+            .map(|(connector, info)| {
+                let make = str_field(info, "make");
+                let model = str_field(info, "model");
+                let serial = str_field(info, "serial");
+                let description =
+                    compose_description(make.as_deref(), model.as_deref(), serial.as_deref());
+                OutputInfo {
+                    connector: connector.clone(),
+                    make,
+                    model,
+                    serial,
+                    description,
+                }
+            })
+            .collect()
+    }
+}
+
+/* ----------------------------- Hyprland ------------------------------ */
+
+pub struct HyprlandBackend;
+
+impl CompositorBackend for HyprlandBackend {
+    fn list_outputs(&self) -> Vec<OutputInfo> {
+        let Some(val) = run_json("hyprctl", &["-j", "monitors"]) else {
+            return Vec::new();
+        };
+        let Some(arr) = val.as_array() else {
+            return Vec::new();
+        };
+        arr.iter()
+            .filter_map(|m| {
+                let connector = m.get("name")?.as_str()?.to_string();
+                Some(OutputInfo {
+                    connector,
+                    make: str_field(m, "make"),
+                    model: str_field(m, "model"),
+                    serial: str_field(m, "serial"),
+                    description: str_field(m, "description"),
+                })
+            })
+            .collect()
+    }
+}
+
+fn str_field(v: &serde_json::Value, key: &str) -> Option<String> {
+    v.get(key).and_then(|x| x.as_str()).map(String::from)
+}
+
+fn run_json(cmd: &str, args: &[&str]) -> Option<serde_json::Value> {
+    let out = Command::new(cmd)
+        .args(args)
+        .stdout(Stdio::piped())
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    serde_json::from_slice(&out.stdout).ok()
+}
+
+/* --------------------------- Scored matching --------------------------- */
+
+/// Case-insensitive, whitespace-collapsing normalization so a trailing space
+/// or re-flowed description doesn't break identity matching.
+fn normalize_ident(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Whether `needle` occurs in `haystack` as a run of whole space-separated
+/// words, not merely as a substring -- so a model like "27" doesn't
+/// spuriously match inside "xf270h". Both arguments are expected already
+/// normalized (lowercased, whitespace-collapsed).
+fn contains_words(haystack: &str, needle: &str) -> bool {
+    !needle.is_empty() && format!(" {haystack} ").contains(&format!(" {needle} "))
+}
+
+/// Build the same kind of "make model serial" identity string Sway reports
+/// as `description`, for backends whose JSON doesn't supply one natively.
+fn compose_description(make: Option<&str>, model: Option<&str>, serial: Option<&str>) -> Option<String> {
+    let parts: Vec<&str> = [make, model, serial].into_iter().flatten().collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// Match quality, highest-confidence first: an exact serial match wins
+/// outright since serials are the stable EDID identity; make+model is next
+/// most specific, then model alone, and finally a plain description
+/// substring as a last resort. The serial, make and model tiers look for the
+/// candidate's (normalized) field as a whole-word run inside the full hint,
+/// rather than splitting the hint into assumed make/model tokens; the
+/// description tier is a plain substring test in either direction. EDID vendor strings
+/// routinely span multiple words ("Dell Inc", "Hewlett Packard", "Acer
+/// Technologies"), so "first word is the make" doesn't hold in practice.
+const SCORE_DESCRIPTION_SUBSTRING: u8 = 40;
+const SCORE_MODEL: u8 = 60;
+const SCORE_MAKE_MODEL: u8 = 80;
+const SCORE_SERIAL: u8 = 100;
+const SCORE_THRESHOLD: u8 = SCORE_DESCRIPTION_SUBSTRING;
+
+fn score_candidate(normalized_hint: &str, candidate: &OutputInfo) -> u8 {
+    let cand_serial = candidate.serial.as_deref().map(normalize_ident);
+    let cand_make = candidate.make.as_deref().map(normalize_ident);
+    let cand_model = candidate.model.as_deref().map(normalize_ident);
+    let cand_desc = candidate
+        .description
+        .clone()
+        .or_else(|| compose_description(candidate.make.as_deref(), candidate.model.as_deref(), candidate.serial.as_deref()))
+        .map(|d| normalize_ident(&d));
+
+    if let Some(serial) = &cand_serial {
+        if contains_words(normalized_hint, serial) {
+            return SCORE_SERIAL;
+        }
+    }
+
+    if let (Some(make), Some(model)) = (&cand_make, &cand_model) {
+        if contains_words(normalized_hint, make) && contains_words(normalized_hint, model) {
+            return SCORE_MAKE_MODEL;
+        }
+    }
+
+    if let Some(model) = &cand_model {
+        if contains_words(normalized_hint, model) {
+            return SCORE_MODEL;
+        }
+    }
+
+    if let Some(cand_desc) = &cand_desc {
+        if !cand_desc.is_empty()
+            && !normalized_hint.is_empty()
+            && (cand_desc.contains(normalized_hint) || normalized_hint.contains(cand_desc.as_str()))
+        {
+            return SCORE_DESCRIPTION_SUBSTRING;
+        }
+    }
+
+    0
+}
+
+/// Whether `candidate` matches a config rule's make/model/serial fields,
+/// compared via the same normalization `resolve_hint_scored` uses so a
+/// trailing space or case difference in `config.kdl` doesn't cause a miss.
+/// A `None` rule field matches anything, mirroring `pick_from_config`'s
+/// original wildcard behavior.
+pub fn identity_matches(
+    make: Option<&str>,
+    model: Option<&str>,
+    serial: Option<&str>,
+    candidate: &OutputInfo,
+) -> bool {
+    let field_matches = |want: Option<&str>, have: Option<&str>| match want {
+        None => true,
+        Some(want) => have.is_some_and(|have| normalize_ident(want) == normalize_ident(have)),
+    };
+
+    field_matches(make, candidate.make.as_deref())
+        && field_matches(model, candidate.model.as_deref())
+        && field_matches(serial, candidate.serial.as_deref())
+}
+
+/// Try to map a hint (either a connector like "DP-2" or a make/model/serial
+/// identity) to a connector name among `outputs`, via scored identification
+/// rather than a single exact-equality check.
+pub fn resolve_hint_scored(outputs: &[OutputInfo], hint: &str) -> Option<String> {
+    use regex::Regex;
+
+    // If hint already looks like a connector (DP-#, HDMI-#, eDP-#), just return it.
+    if Regex::new(r"^(e?DP|HDMI|DVI|VGA|USB-C|LVDS|Virtual|X11)-")
+        .unwrap()
+        .is_match(hint)
+    {
+        return Some(hint.to_string());
+    }
+
+    let normalized_hint = normalize_ident(hint);
+
+    let mut scored: Vec<(u8, &OutputInfo)> = outputs
+        .iter()
+        .map(|o| (score_candidate(&normalized_hint, o), o))
+        .filter(|(score, _)| *score >= SCORE_THRESHOLD)
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+    let (best_score, best) = *scored.first()?;
+    let tied: Vec<&str> = scored
+        .iter()
+        .skip(1)
+        .filter(|(score, _)| *score == best_score)
+        .map(|(_, o)| o.connector.as_str())
+        .collect();
+    if !tied.is_empty() {
+        notify_info(&format!(
+            "Ambiguous match for hint \"{hint}\": also matched {}; picking {}.",
+            tied.join(", "),
+            best.connector
+        ));
+    }
+
+    Some(best.connector.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(connector: &str, make: &str, model: &str, serial: &str) -> OutputInfo {
+        OutputInfo {
+            connector: connector.to_string(),
+            make: Some(make.to_string()),
+            model: Some(model.to_string()),
+            serial: Some(serial.to_string()),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn resolve_hint_scored_matches_multi_word_make_and_model() {
+        let outputs = vec![
+            output("DP-2", "Dell Inc", "DELL P2415Q", "ABC123"),
+            output("HDMI-0", "Acer Technologies", "XF270H", "XYZ789"),
+        ];
+
+        assert_eq!(
+            resolve_hint_scored(&outputs, "Dell Inc DELL P2415Q"),
+            Some("DP-2".to_string())
+        );
+        assert_eq!(
+            resolve_hint_scored(&outputs, "Acer Technologies XF270H"),
+            Some("HDMI-0".to_string())
+        );
+    }
+
+    #[test]
+    fn resolve_hint_scored_matches_model_alone_with_multi_word_make() {
+        let outputs = vec![output(
+            "DP-2",
+            "Hewlett Packard",
+            "HP 27f",
+            "SERIALHP1",
+        )];
+
+        assert_eq!(
+            resolve_hint_scored(&outputs, "HP 27f"),
+            Some("DP-2".to_string())
+        );
+    }
+
+    #[test]
+    fn resolve_hint_scored_matches_serial_regardless_of_surrounding_text() {
+        let outputs = vec![output(
+            "DP-2",
+            "Acer Technologies",
+            "Acer XF270H B",
+            "0x9372943C",
+        )];
+
+        assert_eq!(
+            resolve_hint_scored(&outputs, "Acer Technologies Acer XF270H B 0x9372943C"),
+            Some("DP-2".to_string())
+        );
+    }
+
+    #[test]
+    fn resolve_hint_scored_falls_through_to_description_substring() {
+        let mut outputs = vec![output("DP-2", "Samsung Electronics Company Ltd", "U28E590", "S123")];
+        outputs[0].description = Some("Samsung Electronics Company Ltd U28E590 S123".to_string());
+        // Without a model field the model tier can't match, leaving only the
+        // description substring.
+        outputs[0].model = None;
+
+        assert_eq!(
+            resolve_hint_scored(&outputs, "U28E590"),
+            Some("DP-2".to_string())
+        );
+    }
+
+    #[test]
+    fn resolve_hint_scored_returns_none_on_no_match() {
+        let outputs = vec![output("DP-2", "Dell Inc", "DELL P2415Q", "ABC123")];
+        assert_eq!(resolve_hint_scored(&outputs, "totally unrelated"), None);
+    }
+}
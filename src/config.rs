@@ -0,0 +1,64 @@
+//! First-class configuration format: an ordered list of monitor
+//! preferences in `~/.config/x11-primary-switcher/config.kdl`, parsed with
+//! `knuffel`. Replaces the fragile Sway-config-comment scraping with a real,
+//! documented priority list; `read_preferred_from_sway_config` in `main.rs`
+//! remains as a legacy fallback for users who haven't migrated yet.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// An `output` rule from the config file: a connector name, a make/model/serial
+/// identity (or both), tried in document order by `--default`.
+#[derive(Debug, knuffel::Decode)]
+pub struct OutputRule {
+    /// Connector name to match directly, e.g. `output "DP-2"`.
+    #[knuffel(argument, default)]
+    pub target: Option<String>,
+    #[knuffel(property, default)]
+    pub make: Option<String>,
+    #[knuffel(property, default)]
+    pub model: Option<String>,
+    #[knuffel(property, default)]
+    pub serial: Option<String>,
+}
+
+/// Top-level node of `config.kdl`. Only `output` is recognized; wrapping
+/// `OutputRule` in this enum (matched on the KDL node name) means a typo'd
+/// node name or a future non-output directive fails to parse instead of
+/// silently decoding as a monitor rule.
+#[derive(Debug, knuffel::Decode)]
+pub enum ConfigNode {
+    Output(OutputRule),
+}
+
+/// The ordered set of `output` rules, highest priority first.
+#[derive(Debug)]
+pub struct Outputs(pub Vec<OutputRule>);
+
+/// Parsed `config.kdl`.
+#[derive(Debug)]
+pub struct Config {
+    pub outputs: Outputs,
+}
+
+pub fn default_config_path() -> PathBuf {
+    let home = env::var("HOME").expect("HOME is not set");
+    PathBuf::from(home).join(".config/x11-primary-switcher/config.kdl")
+}
+
+/// Load and parse `path` as a KDL config. Returns `None` if the file doesn't
+/// exist or fails to parse, so callers can fall back to the legacy Sway
+/// comment-block parser.
+pub fn load(path: &Path) -> Option<Config> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let nodes = knuffel::parse::<Vec<ConfigNode>>(&path.to_string_lossy(), &text)
+        .map_err(|e| eprintln!("Warning: failed to parse {}: {e}", path.display()))
+        .ok()?;
+    let rules = nodes
+        .into_iter()
+        .map(|ConfigNode::Output(rule)| rule)
+        .collect();
+    Some(Config {
+        outputs: Outputs(rules),
+    })
+}
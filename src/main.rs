@@ -1,12 +1,21 @@
 use clap::{Arg, Command as ClapCommand};
 use notify_rust::{Hint, Notification, Timeout};
 use regex::Regex;
-use serde::Deserialize;
 use std::env;
 use std::fs;
 use std::io::{self, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
+use std::time::Duration;
+use udev::MonitorBuilder;
+
+mod compositor;
+mod config;
+mod mutter;
+mod sway_ipc;
+use compositor::CompositorBackend;
+use sway_ipc::IpcConnection;
 
 const APP_NAME: &str = "x11-primary-switcher";
 const APP_SUMMARY: &str = "X11 Primary Monitor Switcher";
@@ -39,19 +48,109 @@ fn main() {
                 .value_name("PATH")
                 .help("Path to Sway config (default: ~/.config/sway/config)"),
         )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .help("Run as a daemon: re-apply the default primary whenever outputs change")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("watch-sway")
+                .long("watch-sway")
+                .help("Like --watch, but reacts instantly via the Sway IPC output event subscription")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("compositor")
+                .long("compositor")
+                .value_name("sway|niri|hyprland")
+                .help("Compositor to query for output hints (default: probed from $SWAYSOCK/$NIRI_SOCKET/$HYPRLAND_INSTANCE_SIGNATURE)"),
+        )
         .get_matches();
 
-    // Ensure xrandr is usable (i.e., XWayland is running)
-    if !cmd_ok("xrandr", &["--query"]) {
-        notify_error(
-            "xrandr --query failed. Are you in a Wayland session with XWayland? Is xrandr installed?",
-        );
-        eprintln!("Error: xrandr --query failed.");
-        std::process::exit(1);
+    let compositor_kind = matches
+        .get_one::<String>("compositor")
+        .and_then(|s| compositor::parse_compositor_flag(s))
+        .or_else(compositor::detect);
+    let backend: Option<Box<dyn CompositorBackend>> = compositor_kind.map(compositor::backend_for);
+
+    // Flags
+    let auto = matches.get_flag("auto-switch");
+    let use_default = matches.get_flag("default");
+    let status = matches.get_flag("status");
+    let watch = matches.get_flag("watch");
+    let watch_sway = matches.get_flag("watch-sway");
+
+    // --watch/--watch-sway are long-running daemons; they drive xrandr
+    // themselves on every iteration, so they must enter their loop
+    // unconditionally rather than being redirected into a single Mutter
+    // apply-and-exit just because xrandr isn't usable yet at startup.
+    if watch_sway {
+        let cfg_path = matches
+            .get_one::<String>("config")
+            .map(PathBuf::from)
+            .unwrap_or_else(default_sway_config);
+        run_watch_sway_mode(cfg_path);
+    }
+
+    if watch {
+        let cfg_path = matches
+            .get_one::<String>("config")
+            .map(PathBuf::from)
+            .unwrap_or_else(default_sway_config);
+        run_watch_mode(cfg_path, backend.as_deref());
+    }
+
+    // --default is the one-shot "apply the configured primary" case, and the
+    // only mode that has a sensible Mutter-DBus equivalent when XWayland
+    // doesn't have a usable primary concept (e.g. plain GNOME Wayland).
+    if use_default {
+        if !cmd_ok("xrandr", &["--query"]) {
+            match run_mutter_fallback(&matches, backend.as_deref()) {
+                Ok(target) => {
+                    notify_ok(&format!("Primary set via Mutter DBus -> {}", target));
+                    return;
+                }
+                Err(e) => {
+                    notify_error(&format!(
+                        "xrandr --query failed and the Mutter DBus fallback also failed: {e}"
+                    ));
+                    eprintln!("Error: xrandr --query failed and Mutter fallback failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        let outputs = match xrandr_list_outputs() {
+            Ok(v) if !v.is_empty() => v,
+            _ => {
+                notify_error("No connected X11 outputs found.");
+                eprintln!("No connected X11 outputs found.");
+                std::process::exit(1);
+            }
+        };
+
+        let cfg_path = matches
+            .get_one::<String>("config")
+            .map(PathBuf::from)
+            .unwrap_or_else(default_sway_config);
+
+        let connected: Vec<String> = outputs.iter().map(|o| o.name.clone()).collect();
+        let chosen = resolve_default_target(&cfg_path, &connected, backend.as_deref());
+
+        if set_primary(&chosen) {
+            notify_ok(&format!("Primary set (default mode) -> {}", chosen));
+        } else {
+            notify_error(&format!("Failed to set primary to {}", chosen));
+            std::process::exit(1);
+        }
+        return;
     }
 
-    // Read current X11 outputs
-    let mut outputs = match xrandr_list_outputs() {
+    // Every remaining mode (--status, --auto-switch, interactive) reads or
+    // sets the X11 primary directly, so it needs a working xrandr; none of
+    // them has a Mutter-DBus equivalent worth silently switching into.
+    let outputs = match xrandr_list_outputs() {
         Ok(v) if !v.is_empty() => v,
         _ => {
             notify_error("No connected X11 outputs found.");
@@ -60,24 +159,18 @@ fn main() {
         }
     };
 
-    // --status: print current primary and exit
-    if matches.get_flag("status") {
+    // --status: print current primary and exit (read-only, never applies a change)
+    if status {
         let (_idx, name) = current_primary_index_name(&outputs);
-        // Print only the name, as requested ("simply print")
         if let Some(n) = name {
             println!("Primary monitor: {n}.");
             std::process::exit(0);
         } else {
-            // No primary set: print nothing or a marker; here we print "(none)"
             println!("(none)");
             std::process::exit(1); // non-zero to indicate no primary; change to 0 if you prefer
         }
     }
 
-    // Flags
-    let auto = matches.get_flag("auto-switch");
-    let use_default = matches.get_flag("default");
-
     if auto {
         // Cycle to the next connected output after current primary
         let (current_idx, current_name) = current_primary_index_name(&outputs);
@@ -96,48 +189,6 @@ fn main() {
         return;
     }
 
-    if use_default {
-        // Try to find preferred monitor from Sway config block
-        let cfg_path = matches
-            .get_one::<String>("config")
-            .map(PathBuf::from)
-            .unwrap_or_else(default_sway_config);
-
-        let preferred = read_preferred_from_sway_config(&cfg_path);
-        let target_output_name = match preferred {
-            Some(hint) => {
-                // Map the Sway "nice" identifier to a connector name via swaymsg JSON
-                match map_sway_hint_to_connector(&hint) {
-                    Some(name) => name,
-                    None => {
-                        // Maybe the hint is already a connector like "DP-2"
-                        hint
-                    }
-                }
-            }
-            None => {
-                notify_info("No primary monitor set in Sway config. Choosing first monitor.");
-                outputs[0].name.clone()
-            }
-        };
-
-        // Verify target exists in X11 outputs; if not, fall back to first
-        let exists = outputs.iter().any(|o| o.name == target_output_name);
-        let chosen = if exists {
-            target_output_name
-        } else {
-            outputs[0].name.clone()
-        };
-
-        if set_primary(&chosen) {
-            notify_ok(&format!("Primary set (default mode) -> {}", chosen));
-        } else {
-            notify_error(&format!("Failed to set primary to {}", chosen));
-            std::process::exit(1);
-        }
-        return;
-    }
-
     // Interactive mode
     println!("Detected X11 outputs:");
     for (i, o) in outputs.iter().enumerate() {
@@ -255,6 +306,108 @@ fn default_sway_config() -> PathBuf {
     PathBuf::from(home).join(".config/sway/config")
 }
 
+/// Resolve the connector that `--default`/`--watch` should make primary.
+///
+/// Prefers the ordered `output` list in `config.kdl` if present; falls back
+/// to scraping the legacy Sway-config comment block, and finally to the
+/// first connected output if neither yields a currently connected match.
+fn resolve_default_target(
+    cfg_path: &PathBuf,
+    connected: &[String],
+    backend: Option<&dyn CompositorBackend>,
+) -> String {
+    if let Some(cfg) = config::load(&config::default_config_path()) {
+        if let Some(name) = pick_from_config(&cfg, connected, backend) {
+            return name;
+        }
+        notify_info("config.kdl present but none of its outputs are currently connected; falling back to Sway config.");
+    }
+
+    let preferred = read_preferred_from_sway_config(cfg_path);
+    let target_output_name = match preferred {
+        Some(hint) => {
+            // Map the compositor "nice" identifier to a connector name
+            match backend.and_then(|b| b.resolve_hint(&hint)) {
+                Some(name) => name,
+                None => {
+                    // Maybe the hint is already a connector like "DP-2"
+                    hint
+                }
+            }
+        }
+        None => {
+            notify_info("No primary monitor set in Sway config. Choosing first monitor.");
+            connected[0].clone()
+        }
+    };
+
+    // Verify target is currently connected; if not, fall back to first
+    if connected.iter().any(|n| n == &target_output_name) {
+        target_output_name
+    } else {
+        connected[0].clone()
+    }
+}
+
+/* ----------------------- config.kdl helpers ------------------------ */
+
+/// Walk `cfg`'s ordered output list and return the connector of the first
+/// rule that is currently connected, trying the next rule on a miss.
+fn pick_from_config(
+    cfg: &config::Config,
+    connected: &[String],
+    backend: Option<&dyn CompositorBackend>,
+) -> Option<String> {
+    for rule in &cfg.outputs.0 {
+        if let Some(target) = &rule.target {
+            if connected.iter().any(|n| n == target) {
+                return Some(target.clone());
+            }
+        }
+
+        if rule.make.is_some() || rule.model.is_some() || rule.serial.is_some() {
+            if let Some(backend) = backend {
+                for candidate in backend.list_outputs() {
+                    let matches = compositor::identity_matches(
+                        rule.make.as_deref(),
+                        rule.model.as_deref(),
+                        rule.serial.as_deref(),
+                        &candidate,
+                    );
+                    if matches && connected.iter().any(|n| n == &candidate.connector) {
+                        return Some(candidate.connector.clone());
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/* --------------------------- Mutter DBus fallback --------------------------- */
+
+/// Resolve the configured primary the same way `--default` does, then apply
+/// it through `mutter::apply_primary` instead of `xrandr`. Used when
+/// `xrandr --query` fails outright, i.e. there's no XWayland primary to set.
+fn run_mutter_fallback(
+    matches: &clap::ArgMatches,
+    backend: Option<&dyn CompositorBackend>,
+) -> Result<String, String> {
+    let cfg_path = matches
+        .get_one::<String>("config")
+        .map(PathBuf::from)
+        .unwrap_or_else(default_sway_config);
+
+    let monitors = mutter::list_outputs()?;
+    if monitors.is_empty() {
+        return Err("Mutter reported no monitors".to_string());
+    }
+    let connected: Vec<String> = monitors.iter().map(|o| o.connector.clone()).collect();
+
+    let hint = resolve_default_target(&cfg_path, &connected, backend);
+    mutter::apply_primary(&hint)
+}
+
 /// Parse the Primary Monitor block:
 /// #! Primary Monitor Start !#
 /// output "Acer Technologies Acer XF270H B 0x9372943C" resolution ...
@@ -309,71 +462,159 @@ fn read_preferred_from_sway_config(path: &PathBuf) -> Option<String> {
     None
 }
 
-/* ----------------- Map Sway "nice" to connector name -------------- */
+/* --------------------------- Watch (daemon) mode ------------------------- */
+
+/// How long to keep coalescing bursty udev events before re-evaluating the
+/// primary. Monitors commonly fire several `change` events per hotplug.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Run forever, re-applying the default primary (from `config.kdl`, else the
+/// Sway config, via `resolve_default_target`) whenever the
+/// kernel reports a DRM output change (connect/disconnect) over udev. Only
+/// calls `set_primary` when the newly resolved connector actually differs
+/// from the current primary.
+fn run_watch_mode(cfg_path: PathBuf, backend: Option<&dyn CompositorBackend>) -> ! {
+    let socket = MonitorBuilder::new()
+        .and_then(|b| b.match_subsystem("drm"))
+        .and_then(|b| b.listen())
+        .unwrap_or_else(|e| {
+            notify_error(&format!("Failed to open udev monitor: {e}"));
+            eprintln!("Error: failed to open udev monitor: {e}");
+            std::process::exit(1);
+        });
 
-#[derive(Debug, Deserialize)]
-struct SwayOutput {
-    name: String,                // e.g., "DP-2"
-    make: Option<String>,        // e.g., "Acer Technologies"
-    model: Option<String>,       // e.g., "Acer XF270H B"
-    serial: Option<String>,      // e.g., "0x9372943C" (or actual serial)
-    description: Option<String>, // e.g., "Acer Technologies Acer XF270H B 0x9372943C"
-}
+    let fd = socket.as_raw_fd();
+    println!("Watching for DRM hotplug events via udev (Ctrl-C to stop)...");
 
-#[derive(Debug, Deserialize)]
-struct SwayOutputs(Vec<SwayOutput>);
+    loop {
+        // Block until the udev socket has something to read.
+        if !poll_readable(fd, None) {
+            continue;
+        }
 
-/// Try to map a Sway output hint (either connector like "DP-2" or description like
-/// "Acer Technologies Acer XF270H B 0x9372943C") to the connector name.
-fn map_sway_hint_to_connector(hint: &str) -> Option<String> {
-    // If hint already looks like a connector (DP-#, HDMI-#, eDP-#), just return it.
-    if Regex::new(r"^(e?DP|HDMI|DVI|VGA|USB-C|LVDS|Virtual|X11)-")
-        .unwrap()
-        .is_match(hint)
-    {
-        return Some(hint.to_string());
+        // Drain everything that is already queued, then keep polling with a
+        // short timeout to coalesce the rest of the burst before acting once.
+        let mut saw_event = false;
+        loop {
+            let mut drained_any = false;
+            for event in socket.iter() {
+                let _ = event;
+                saw_event = true;
+                drained_any = true;
+            }
+            if !drained_any || !poll_readable(fd, Some(WATCH_DEBOUNCE)) {
+                break;
+            }
+        }
+
+        if !saw_event {
+            continue;
+        }
+
+        let outputs = match xrandr_list_outputs() {
+            Ok(v) if !v.is_empty() => v,
+            _ => continue,
+        };
+
+        let (_idx, current_name) = current_primary_index_name(&outputs);
+        let connected: Vec<String> = outputs.iter().map(|o| o.name.clone()).collect();
+        let target = resolve_default_target(&cfg_path, &connected, backend);
+
+        if current_name.as_deref() == Some(target.as_str()) {
+            continue;
+        }
+
+        if set_primary(&target) {
+            notify_ok(&format!(
+                "Outputs changed, primary re-applied: {} -> {}.",
+                current_name.unwrap_or_else(|| "none".into()),
+                target
+            ));
+        } else {
+            notify_error(&format!("Failed to set primary to {} after hotplug.", target));
+        }
     }
+}
 
-    // Else query sway outputs
-    let out = Command::new("swaymsg")
-        .args(["-t", "get_outputs"])
-        .stdout(Stdio::piped())
-        .output()
-        .ok()?;
-    if !out.status.success() {
-        return None;
+/// How long to wait between reconnect attempts once the Sway IPC socket has
+/// dropped, so a permanently-gone `$SWAYSOCK` (Sway exited for good) doesn't
+/// turn this daemon into a busy loop pegging a CPU core.
+const SWAY_RECONNECT_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Run forever, reacting to Sway's own `output` IPC events instead of
+/// polling or watching udev. Avoids spawning `swaymsg` repeatedly and reacts
+/// the moment Sway (or another tool) changes the output layout.
+fn run_watch_sway_mode(cfg_path: PathBuf) -> ! {
+    let mut conn = IpcConnection::connect().unwrap_or_else(|e| {
+        notify_error(&format!("Failed to connect to Sway IPC ($SWAYSOCK): {e}"));
+        eprintln!("Error: failed to connect to Sway IPC: {e}");
+        std::process::exit(1);
+    });
+
+    if let Err(e) = conn.subscribe_outputs() {
+        notify_error(&format!("Failed to subscribe to Sway output events: {e}"));
+        eprintln!("Error: failed to subscribe to Sway output events: {e}");
+        std::process::exit(1);
     }
-    let json = String::from_utf8_lossy(&out.stdout);
-    let vals: serde_json::Value = serde_json::from_str(&json).ok()?;
-    let arr = vals.as_array()?;
-
-    for v in arr {
-        let name = v
-            .get("name")
-            .and_then(|x| x.as_str())
-            .unwrap_or("")
-            .to_string();
-        let desc = v
-            .get("description")
-            .and_then(|x| x.as_str())
-            .unwrap_or("")
-            .to_string();
-
-        // Exact match against description first
-        if !desc.is_empty() && desc == hint {
-            return Some(name);
+
+    println!("Watching Sway output events via IPC (Ctrl-C to stop)...");
+
+    loop {
+        if conn.next_output_event().is_err() {
+            // Sway restarted or the socket dropped; reconnect and re-subscribe.
+            conn = match IpcConnection::connect().and_then(|mut c| {
+                c.subscribe_outputs()?;
+                Ok(c)
+            }) {
+                Ok(c) => c,
+                Err(_) => {
+                    std::thread::sleep(SWAY_RECONNECT_BACKOFF);
+                    continue;
+                }
+            };
+            continue;
+        }
+
+        let outputs = match xrandr_list_outputs() {
+            Ok(v) if !v.is_empty() => v,
+            _ => continue,
+        };
+
+        let (_idx, current_name) = current_primary_index_name(&outputs);
+        let connected: Vec<String> = outputs.iter().map(|o| o.name.clone()).collect();
+        let target = resolve_default_target(
+            &cfg_path,
+            &connected,
+            Some(&compositor::SwayBackend as &dyn CompositorBackend),
+        );
+
+        if current_name.as_deref() == Some(target.as_str()) {
+            continue;
         }
 
-        // Fallback: make + model + serial concatenation
-        let make = v.get("make").and_then(|x| x.as_str()).unwrap_or("");
-        let model = v.get("model").and_then(|x| x.as_str()).unwrap_or("");
-        let serial = v.get("serial").and_then(|x| x.as_str()).unwrap_or("");
-        let combo = format!("{} {} {}", make, model, serial).trim().to_string();
-        if !combo.is_empty() && combo == hint {
-            return Some(name);
+        if set_primary(&target) {
+            notify_ok(&format!(
+                "Sway output event, primary re-applied: {} -> {}.",
+                current_name.unwrap_or_else(|| "none".into()),
+                target
+            ));
+        } else {
+            notify_error(&format!("Failed to set primary to {} after Sway event.", target));
         }
     }
-    None
+}
+
+/// Block (with an optional timeout) until `fd` becomes readable. Returns
+/// `false` on timeout or interrupted/failed poll.
+fn poll_readable(fd: RawFd, timeout: Option<Duration>) -> bool {
+    let mut pfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout_ms = timeout.map(|d| d.as_millis() as i32).unwrap_or(-1);
+    let ret = unsafe { libc::poll(&mut pfd, 1, timeout_ms) };
+    ret > 0 && (pfd.revents & libc::POLLIN) != 0
 }
 
 /* --------------------------- Notifications ------------------------ */
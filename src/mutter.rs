@@ -0,0 +1,114 @@
+//! Apply the primary output via GNOME's `org.gnome.Mutter.DisplayConfig` DBus
+//! interface. On GNOME Wayland there's no usable XWayland `xrandr --primary`,
+//! so this is the fallback path used when `xrandr --query` itself fails: we
+//! identify the desired monitor the same way as the other backends (by
+//! make/model/serial) and flag it primary via `ApplyMonitorsConfig`, leaving
+//! every other monitor's position/scale/transform untouched.
+
+use std::collections::HashMap;
+
+use zbus::blocking::Connection;
+use zbus::proxy;
+use zbus::zvariant::OwnedValue;
+
+use crate::compositor::{resolve_hint_scored, OutputInfo};
+
+type MonitorSpec = (String, String, String, String); // connector, vendor, product, serial
+type ModeInfo = (String, i32, i32, f64, f64, Vec<f64>, HashMap<String, OwnedValue>);
+type MonitorInfo = (MonitorSpec, Vec<ModeInfo>, HashMap<String, OwnedValue>);
+type LogicalMonitorInfo = (i32, i32, f64, u32, bool, Vec<MonitorSpec>, HashMap<String, OwnedValue>);
+type RequestMonitor = (String, String, HashMap<String, OwnedValue>);
+type RequestLogicalMonitor = (i32, i32, f64, u32, bool, Vec<RequestMonitor>);
+type CurrentState = (u32, Vec<MonitorInfo>, Vec<LogicalMonitorInfo>, HashMap<String, OwnedValue>);
+
+/// Persist the new layout (as opposed to `1`, a temporary preview Mutter
+/// reverts if the client doesn't confirm it).
+const APPLY_METHOD_PERSISTENT: u32 = 2;
+
+#[proxy(
+    interface = "org.gnome.Mutter.DisplayConfig",
+    default_service = "org.gnome.Mutter.DisplayConfig",
+    default_path = "/org/gnome/Mutter/DisplayConfig"
+)]
+trait DisplayConfig {
+    fn get_current_state(&self) -> zbus::Result<CurrentState>;
+
+    #[zbus(name = "ApplyMonitorsConfig")]
+    fn apply_monitors_config(
+        &self,
+        serial: u32,
+        method: u32,
+        logical_monitors: Vec<RequestLogicalMonitor>,
+        properties: HashMap<String, OwnedValue>,
+    ) -> zbus::Result<()>;
+}
+
+fn monitor_to_output_info(monitor: &MonitorInfo) -> OutputInfo {
+    let (connector, vendor, product, serial) = &monitor.0;
+    let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+    OutputInfo {
+        connector: connector.clone(),
+        make: non_empty(vendor),
+        model: non_empty(product),
+        serial: non_empty(serial),
+        description: None,
+    }
+}
+
+/// The mode currently active on `connector`, so re-applying the config
+/// doesn't change resolution/refresh rate.
+fn current_mode_id(monitors: &[MonitorInfo], connector: &str) -> Option<String> {
+    let (_, modes, _) = monitors.iter().find(|m| m.0 .0 == connector)?;
+    modes.iter().find_map(|(mode_id, _, _, _, _, _, props)| {
+        let is_current = props
+            .get("is-current")
+            .and_then(|v| bool::try_from(v).ok())
+            .unwrap_or(false);
+        is_current.then(|| mode_id.clone())
+    })
+}
+
+/// Set `hint`'s resolved connector as primary via `ApplyMonitorsConfig`,
+/// preserving every logical monitor's position/scale/transform/membership.
+/// Returns the connector that was made primary.
+pub fn apply_primary(hint: &str) -> Result<String, String> {
+    let conn = Connection::session().map_err(|e| e.to_string())?;
+    let proxy = DisplayConfigProxyBlocking::new(&conn).map_err(|e| e.to_string())?;
+    let (serial, monitors, logical_monitors, _props) =
+        proxy.get_current_state().map_err(|e| e.to_string())?;
+
+    let infos: Vec<OutputInfo> = monitors.iter().map(monitor_to_output_info).collect();
+    let target = resolve_hint_scored(&infos, hint)
+        .or_else(|| infos.first().map(|o| o.connector.clone()))
+        .ok_or_else(|| "Mutter reported no monitors".to_string())?;
+
+    let request: Vec<RequestLogicalMonitor> = logical_monitors
+        .into_iter()
+        .map(|(x, y, scale, transform, _primary, monitor_specs, _props)| {
+            let is_target = monitor_specs.iter().any(|(connector, ..)| *connector == target);
+            let request_monitors: Vec<RequestMonitor> = monitor_specs
+                .into_iter()
+                .map(|(connector, ..)| {
+                    let mode_id = current_mode_id(&monitors, &connector).unwrap_or_default();
+                    (connector, mode_id, HashMap::new())
+                })
+                .collect();
+            (x, y, scale, transform, is_target, request_monitors)
+        })
+        .collect();
+
+    proxy
+        .apply_monitors_config(serial, APPLY_METHOD_PERSISTENT, request, HashMap::new())
+        .map_err(|e| e.to_string())?;
+
+    Ok(target)
+}
+
+/// List the monitors Mutter currently knows about, normalized into the same
+/// `OutputInfo` shape the other compositor backends use.
+pub fn list_outputs() -> Result<Vec<OutputInfo>, String> {
+    let conn = Connection::session().map_err(|e| e.to_string())?;
+    let proxy = DisplayConfigProxyBlocking::new(&conn).map_err(|e| e.to_string())?;
+    let (_serial, monitors, _logical, _props) = proxy.get_current_state().map_err(|e| e.to_string())?;
+    Ok(monitors.iter().map(monitor_to_output_info).collect())
+}
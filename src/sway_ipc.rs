@@ -0,0 +1,95 @@
+//! Minimal client for the binary Sway/i3 IPC protocol (see sway-ipc(7) for
+//! the wire format). Used so we can subscribe to output events and fetch
+//! `get_outputs` over the socket directly instead of spawning `swaymsg` for
+//! every query. `get_outputs` reads the next message as its reply, so it is
+//! called on a fresh connection, not on one subscribed to events.
+
+use serde::Deserialize;
+use std::env;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+
+const MAGIC: &[u8] = b"i3-ipc";
+
+const MSG_TYPE_SUBSCRIBE: u32 = 2;
+const MSG_TYPE_GET_OUTPUTS: u32 = 3;
+const EVENT_OUTPUT: u32 = 0x8000_0001;
+
+/// A single Sway output, as reported by `get_outputs` / `output` events.
+#[derive(Debug, Deserialize)]
+pub struct SwayOutput {
+    pub name: String,                // e.g., "DP-2"
+    pub make: Option<String>,        // e.g., "Acer Technologies"
+    pub model: Option<String>,       // e.g., "Acer XF270H B"
+    pub serial: Option<String>,      // e.g., "0x9372943C" (or actual serial)
+    pub description: Option<String>, // e.g., "Acer Technologies Acer XF270H B 0x9372943C"
+}
+
+/// A connection to the Sway IPC socket (`$SWAYSOCK`), used both for one-off
+/// requests (`get_outputs`) and for subscribing to the `output` event stream.
+pub struct IpcConnection {
+    stream: UnixStream,
+}
+
+impl IpcConnection {
+    /// Connect to the Sway IPC socket named by `$SWAYSOCK`.
+    pub fn connect() -> io::Result<Self> {
+        let path = env::var("SWAYSOCK")
+            .map_err(|_| io::Error::new(io::ErrorKind::NotFound, "SWAYSOCK is not set"))?;
+        let stream = UnixStream::connect(path)?;
+        Ok(Self { stream })
+    }
+
+    fn send(&mut self, msg_type: u32, payload: &str) -> io::Result<()> {
+        let body = payload.as_bytes();
+        let mut buf = Vec::with_capacity(MAGIC.len() + 8 + body.len());
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&msg_type.to_le_bytes());
+        buf.extend_from_slice(body);
+        self.stream.write_all(&buf)
+    }
+
+    /// Read one framed message off the socket, returning its type and raw
+    /// JSON payload.
+    fn recv(&mut self) -> io::Result<(u32, String)> {
+        let mut header = [0u8; 14]; // 6-byte magic + payload-length u32 + type u32
+        self.stream.read_exact(&mut header)?;
+        if &header[0..6] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "bad i3-ipc magic in reply",
+            ));
+        }
+        let len = u32::from_le_bytes(header[6..10].try_into().unwrap()) as usize;
+        let msg_type = u32::from_le_bytes(header[10..14].try_into().unwrap());
+        let mut payload = vec![0u8; len];
+        self.stream.read_exact(&mut payload)?;
+        Ok((msg_type, String::from_utf8_lossy(&payload).into_owned()))
+    }
+
+    /// Fetch the current output list via `GET_OUTPUTS`.
+    pub fn get_outputs(&mut self) -> io::Result<Vec<SwayOutput>> {
+        self.send(MSG_TYPE_GET_OUTPUTS, "")?;
+        let (_, payload) = self.recv()?;
+        serde_json::from_str(&payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Subscribe to the `output` event stream. After this succeeds,
+    /// `next_output_event` can be called repeatedly to block for events.
+    pub fn subscribe_outputs(&mut self) -> io::Result<()> {
+        self.send(MSG_TYPE_SUBSCRIBE, r#"["output"]"#)?;
+        self.recv()?; // subscribe ack, e.g. {"success":true}
+        Ok(())
+    }
+
+    /// Block until the next `output` event arrives on a subscribed connection.
+    pub fn next_output_event(&mut self) -> io::Result<()> {
+        loop {
+            let (msg_type, _payload) = self.recv()?;
+            if msg_type == EVENT_OUTPUT {
+                return Ok(());
+            }
+        }
+    }
+}